@@ -1,11 +1,14 @@
 use dashmap::DashMap;
-use serenity::http::Http;
+use serenity::http::{Http, HttpError};
 use serenity::model::channel::Channel;
 use serenity::model::id::{ChannelId, GuildId};
+use std::error::Error;
 use std::fmt;
 use std::fmt::Display;
 use std::fmt::Formatter;
+use std::hash::Hash;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 #[derive(Clone)]
 pub struct Location {
@@ -48,16 +51,69 @@ impl Location {
     }
 }
 
+/// Why a guild or channel name couldn't be looked up.
+#[derive(Debug)]
+pub enum LocationError {
+    Guild(GuildId, serenity::Error),
+    Channel(ChannelId, serenity::Error),
+}
+
+impl LocationError {
+    fn source_error(&self) -> &serenity::Error {
+        match self {
+            LocationError::Guild(_, e) | LocationError::Channel(_, e) => e,
+        }
+    }
+}
+
+impl Display for LocationError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            LocationError::Guild(id, _) => write!(f, "couldn't fetch guild {}", id)?,
+            LocationError::Channel(id, _) => write!(f, "couldn't fetch channel {}", id)?,
+        }
+
+        if let serenity::Error::Http(http_error) = self.source_error()
+            && let HttpError::UnsuccessfulRequest(response) = &**http_error
+        {
+            write!(f, " (HTTP {})", response.status_code)?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for LocationError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(self.source_error())
+    }
+}
+
+struct CachedLocation {
+    location: Location,
+    guild_id: Option<GuildId>,
+    inserted: Instant,
+}
+
+struct CachedGuild {
+    name: Arc<String>,
+    inserted: Instant,
+}
+
+/// Guild and channel names shared by every `Handler`, bounded in size and age.
 pub struct LocationCache {
-    channel_map: DashMap<ChannelId, Location>,
-    guild_map: DashMap<GuildId, Arc<String>>,
+    channel_map: DashMap<ChannelId, CachedLocation>,
+    guild_map: DashMap<GuildId, CachedGuild>,
+    max_entries: usize,
+    ttl: Duration,
 }
 
 impl LocationCache {
-    pub fn new() -> Self {
+    pub fn new(max_entries: usize, ttl: Duration) -> Self {
         LocationCache {
             channel_map: DashMap::new(),
             guild_map: DashMap::new(),
+            max_entries,
+            ttl,
         }
     }
 
@@ -66,22 +122,19 @@ impl LocationCache {
         channel_id: ChannelId,
         guild_id: Option<GuildId>,
         http: &Http,
-    ) -> Result<Location, ()> {
-        let guild_result = if let Some(id) = guild_id {
-            Some(self.get_and_cache_guild(id, http).await)
+    ) -> Result<Location, LocationError> {
+        let guild_name = if let Some(id) = guild_id {
+            Some(self.get_and_cache_guild(id, http).await?)
         } else {
             None
         };
 
-        let channel_result = http.get_channel(channel_id.0).await;
-
-        let location = match (guild_result, channel_result) {
-            (Some(Ok(guild_name)), Ok(channel)) => Location::new(Some(guild_name), channel),
-            (None, Ok(channel)) => Location::new(None, channel),
-            _ => return Err(()),
-        };
+        let channel = http
+            .get_channel(channel_id.0)
+            .await
+            .map_err(|e| LocationError::Channel(channel_id, e))?;
 
-        Ok(location)
+        Ok(Location::new(guild_name, channel))
     }
 
     pub async fn get_and_cache_location(
@@ -89,34 +142,117 @@ impl LocationCache {
         channel_id: ChannelId,
         guild_id: Option<GuildId>,
         http: &Http,
-    ) -> Result<Location, ()> {
-        if let Some(location) = self.channel_map.get(&channel_id) {
-            return Ok(location.clone());
+    ) -> Result<Location, LocationError> {
+        if let Some(cached) = self.channel_map.get(&channel_id)
+            && cached.inserted.elapsed() < self.ttl
+        {
+            return Ok(cached.location.clone());
         }
 
-        if let Ok(response) = self.make_location_request(channel_id, guild_id, http).await {
-            self.channel_map.insert(channel_id, response.clone());
-            Ok(response)
-        } else {
-            Err(())
-        }
+        let response = self
+            .make_location_request(channel_id, guild_id, http)
+            .await?;
+        make_room(&self.channel_map, self.max_entries, self.ttl, |c| {
+            c.inserted
+        });
+        self.channel_map.insert(
+            channel_id,
+            CachedLocation {
+                location: response.clone(),
+                guild_id,
+                inserted: Instant::now(),
+            },
+        );
+        Ok(response)
     }
 
     pub async fn get_and_cache_guild(
         &self,
         guild_id: GuildId,
         http: &Http,
-    ) -> Result<Arc<String>, ()> {
-        if let Some(guild_name) = self.guild_map.get(&guild_id) {
-            return Ok(guild_name.clone());
+    ) -> Result<Arc<String>, LocationError> {
+        if let Some(cached) = self.guild_map.get(&guild_id)
+            && cached.inserted.elapsed() < self.ttl
+        {
+            return Ok(cached.name.clone());
         }
 
-        if let Ok(response) = http.get_guild(guild_id.0).await {
-            let guild_name = Arc::new(response.name);
-            self.guild_map.insert(guild_id, guild_name.clone());
-            Ok(guild_name)
-        } else {
-            Err(())
+        let response = http
+            .get_guild(guild_id.0)
+            .await
+            .map_err(|e| LocationError::Guild(guild_id, e))?;
+        let guild_name = Arc::new(response.name);
+        make_room(&self.guild_map, self.max_entries, self.ttl, |g| g.inserted);
+        self.guild_map.insert(
+            guild_id,
+            CachedGuild {
+                name: guild_name.clone(),
+                inserted: Instant::now(),
+            },
+        );
+        Ok(guild_name)
+    }
+
+    /// Refreshes a cached channel from a gateway update.
+    pub fn update_channel(&self, channel: Channel) {
+        if let Some(mut cached) = self.channel_map.get_mut(&channel.id()) {
+            let guild_name = cached.location.guild_name.clone();
+            cached.location = Location::new(guild_name, channel);
+            cached.inserted = Instant::now();
+        }
+    }
+
+    pub fn remove_channel(&self, channel_id: ChannelId) {
+        self.channel_map.remove(&channel_id);
+    }
+
+    /// Refreshes a cached guild, and every cached channel in it, from a gateway update.
+    pub fn update_guild(&self, guild_id: GuildId, name: String) {
+        let name = Arc::new(name);
+        if let Some(mut cached) = self.guild_map.get_mut(&guild_id) {
+            cached.name = name.clone();
+            cached.inserted = Instant::now();
+        }
+
+        for mut cached in self.channel_map.iter_mut() {
+            if cached.guild_id == Some(guild_id) {
+                cached.location.guild_name = Some(name.clone());
+            }
+        }
+    }
+
+    pub fn remove_guild(&self, guild_id: GuildId) {
+        self.guild_map.remove(&guild_id);
+        self.channel_map
+            .retain(|_, cached| cached.guild_id != Some(guild_id));
+    }
+}
+
+/// Drops expired entries once `map` is full, then the oldest entry if it's still full.
+fn make_room<K, V>(
+    map: &DashMap<K, V>,
+    max_entries: usize,
+    ttl: Duration,
+    inserted: impl Fn(&V) -> Instant,
+) where
+    K: Eq + Hash + Clone,
+{
+    if map.len() < max_entries {
+        return;
+    }
+
+    map.retain(|_, value| inserted(value).elapsed() < ttl);
+
+    while map.len() >= max_entries.max(1) {
+        let oldest = map
+            .iter()
+            .min_by_key(|entry| inserted(entry.value()))
+            .map(|entry| entry.key().clone());
+        match oldest {
+            Some(key) => {
+                map.remove(&key);
+            }
+            None => break,
         }
     }
 }
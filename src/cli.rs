@@ -0,0 +1,131 @@
+use crate::discord::SnipeResult;
+use crate::history::HistoryFilter;
+use crate::logging::{self, ExitReason};
+use crate::{log_error_and_exit, pretty_error};
+use chrono::NaiveDate;
+use colored::*;
+use log::error;
+use std::env;
+use std::io::{IsTerminal, stdin};
+
+const USAGE: &str = "Usage: longshot [--dry-run] [--headless] [--verbose] [--json-log <path>]
+       longshot check-config [--headless]
+       longshot history [--account <name>] [--guild <id or name>] [--result <result>]
+                        [--since <YYYY-MM-DD>] [--until <YYYY-MM-DD>]";
+
+pub enum Command {
+    Run,
+    CheckConfig,
+    History(HistoryFilter),
+}
+
+pub struct Args {
+    pub command: Command,
+    pub dry_run: bool,
+    pub headless: bool,
+    pub verbose: bool,
+    pub json_log: Option<String>,
+}
+
+impl Args {
+    pub fn parse() -> Self {
+        let mut args = Args {
+            command: Command::Run,
+            dry_run: false,
+            headless: false,
+            verbose: false,
+            json_log: None,
+        };
+        let mut errors = Vec::new();
+        let mut raw_args = env::args().skip(1).peekable();
+
+        match raw_args.peek().map(String::as_str) {
+            Some("check-config") => args.command = Command::CheckConfig,
+            Some("history") => args.command = Command::History(HistoryFilter::default()),
+            _ => {}
+        }
+        if !matches!(args.command, Command::Run) {
+            raw_args.next();
+        }
+
+        while let Some(arg) = raw_args.next() {
+            let value = match arg.as_str() {
+                "--headless" => {
+                    args.headless = true;
+                    continue;
+                }
+                "--verbose" => {
+                    args.verbose = true;
+                    continue;
+                }
+                "--dry-run" if matches!(args.command, Command::Run) => {
+                    args.dry_run = true;
+                    continue;
+                }
+                "--json-log" if matches!(args.command, Command::Run) => raw_args.next(),
+                "--account" | "--guild" | "--result" | "--since" | "--until"
+                    if matches!(args.command, Command::History(_)) =>
+                {
+                    raw_args.next()
+                }
+                _ => {
+                    errors.push(format!("Unknown argument: {}", arg.underline()));
+                    continue;
+                }
+            };
+
+            let Some(value) = value else {
+                errors.push(format!("Missing value for {}", arg.underline()));
+                continue;
+            };
+
+            if let Err(message) = args.apply(&arg, value) {
+                errors.push(message);
+            }
+        }
+
+        // Nobody can press enter without a terminal on stdin, e.g. under systemd
+        args.headless |= !stdin().is_terminal();
+        logging::set_headless(args.headless);
+        logging::set_verbose(args.verbose);
+
+        if !errors.is_empty() {
+            for message in &errors {
+                pretty_error!("{}", message);
+            }
+            log_error_and_exit!(reason: ExitReason::Usage, "{}", USAGE);
+        }
+
+        args
+    }
+
+    fn apply(&mut self, flag: &str, value: String) -> Result<(), String> {
+        if flag == "--json-log" {
+            self.json_log = Some(value);
+            return Ok(());
+        }
+
+        let Command::History(filter) = &mut self.command else {
+            return Ok(());
+        };
+        match flag {
+            "--account" => filter.account = Some(value),
+            "--guild" => filter.guild = Some(value),
+            "--result" => filter.result = Some(parse_result(&value)?),
+            "--since" => filter.since = Some(parse_date(&value)?),
+            "--until" => filter.until = Some(parse_date(&value)?),
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+fn parse_result(value: &str) -> Result<SnipeResult, String> {
+    serde_json::from_value(serde_json::Value::String(value.to_lowercase()))
+        .map_err(|_| format!("Unknown result: {}", value.underline()))
+}
+
+fn parse_date(value: &str) -> Result<NaiveDate, String> {
+    NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .map_err(|_| format!("Invalid date (expected YYYY-MM-DD): {}", value.underline()))
+}
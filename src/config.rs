@@ -1,55 +1,373 @@
-use crate::{log_error_and_exit, pretty_error};
+use crate::logging::ExitReason;
+use crate::secret::Secret;
+use crate::webhook::WebhookTarget;
+use crate::{log_error_and_exit, pretty_error, pretty_success};
 use colored::*;
-use log::error;
+use hyper::Uri;
+use log::{error, info};
 use serde::{Deserialize, Serialize};
 use serenity::model::id::GuildId;
+use std::collections::HashSet;
+use std::env;
+use std::fmt;
 use std::fs::File;
 use std::io::{Read, Write};
+use std::time::Duration;
+
+const DEFAULT_API_BASE: &str = "https://discord.com/api/v9";
+const DEFAULT_LOCATION_CACHE_SIZE: usize = 1000;
+const DEFAULT_LOCATION_CACHE_TTL_SECS: u64 = 60 * 60;
+const MAIN_TOKEN_VAR: &str = "LONGSHOT_MAIN_TOKEN";
+const SUB_TOKENS_VAR: &str = "LONGSHOT_SUB_TOKENS";
+const PLACEHOLDER_TOKEN: &str = "YOUR_TOKEN_HERE";
+const WEBHOOK_HOSTS: [&str; 4] = [
+    "discord.com",
+    "discordapp.com",
+    "ptb.discord.com",
+    "canary.discord.com",
+];
 
 #[derive(Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
 pub struct Config {
-    main_token: String,
+    #[serde(default)]
+    main_token: Secret,
     snipe_on_main_token: bool,
-    sub_tokens: Vec<String>,
+    #[serde(default)]
+    sub_tokens: Vec<Secret>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    secrets_file: Option<String>,
+    #[serde(default)]
     webhook: String,
+    #[serde(default)]
+    webhooks: Vec<WebhookTarget>,
+    #[serde(default)]
+    guild_mode: GuildMode,
+    #[serde(default)]
     guild_blacklist: Vec<u64>,
+    #[serde(default)]
+    guild_allowlist: Vec<u64>,
+    #[serde(default)]
+    ignore_direct_messages: bool,
+    #[serde(default)]
+    dry_run: bool,
+    #[serde(default = "default_api_base")]
+    api_base: String,
+    #[serde(default = "default_location_cache_size")]
+    location_cache_size: usize,
+    #[serde(default = "default_location_cache_ttl_secs")]
+    location_cache_ttl_secs: u64,
+}
+
+/// Whether `guild_blacklist` excludes guilds, or `guild_allowlist` is the only guilds to act in.
+#[derive(Clone, Copy, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GuildMode {
+    #[default]
+    Blacklist,
+    Allowlist,
+}
+
+fn default_api_base() -> String {
+    DEFAULT_API_BASE.to_string()
+}
+
+fn default_location_cache_size() -> usize {
+    DEFAULT_LOCATION_CACHE_SIZE
+}
+
+fn default_location_cache_ttl_secs() -> u64 {
+    DEFAULT_LOCATION_CACHE_TTL_SECS
 }
 
 impl Default for Config {
     fn default() -> Self {
         Config {
-            main_token: "YOUR_TOKEN_HERE".to_string(),
+            main_token: Secret::new(PLACEHOLDER_TOKEN.to_string()),
             snipe_on_main_token: true,
             sub_tokens: Vec::new(),
+            secrets_file: None,
             webhook: "".to_string(),
+            webhooks: Vec::new(),
+            guild_mode: GuildMode::Blacklist,
             guild_blacklist: Vec::new(),
+            guild_allowlist: Vec::new(),
+            ignore_direct_messages: false,
+            dry_run: false,
+            api_base: default_api_base(),
+            location_cache_size: default_location_cache_size(),
+            location_cache_ttl_secs: default_location_cache_ttl_secs(),
         }
     }
 }
 
 impl Config {
-    pub fn main_token(&self) -> String {
-        self.main_token.clone()
+    pub fn main_token(&self) -> &Secret {
+        &self.main_token
     }
 
-    pub fn get_all_sniping_tokens(&self) -> Vec<String> {
+    pub fn get_all_sniping_tokens(&self) -> Vec<Secret> {
         let mut tokens = self.sub_tokens.clone();
         if self.snipe_on_main_token {
-            tokens.insert(0, self.main_token());
+            tokens.insert(0, self.main_token.clone());
         }
         tokens
     }
 
-    pub fn webhook(&self) -> Option<String> {
+    /// Every webhook to notify, including the single `webhook` URL, which gets all results.
+    pub fn webhooks(&self) -> Vec<WebhookTarget> {
+        let mut webhooks = self.webhooks.clone();
         if !self.webhook.is_empty() {
-            Some(self.webhook.clone())
-        } else {
-            None
+            webhooks.insert(
+                0,
+                WebhookTarget {
+                    url: self.webhook.clone(),
+                    results: Vec::new(),
+                },
+            );
+        }
+        webhooks
+    }
+
+    pub fn api_base(&self) -> &str {
+        self.api_base.trim_end_matches('/')
+    }
+
+    pub fn location_cache_size(&self) -> usize {
+        self.location_cache_size
+    }
+
+    pub fn location_cache_ttl(&self) -> Duration {
+        Duration::from_secs(self.location_cache_ttl_secs)
+    }
+
+    pub fn is_dry_run(&self) -> bool {
+        self.dry_run
+    }
+
+    pub fn enable_dry_run(&mut self) {
+        self.dry_run = true;
+    }
+
+    pub fn guild_allowlist(&self) -> Option<&[u64]> {
+        match self.guild_mode {
+            GuildMode::Allowlist => Some(&self.guild_allowlist),
+            GuildMode::Blacklist => None,
+        }
+    }
+
+    /// Whether messages from this guild (or DMs and group DMs, for `None`) should be ignored.
+    pub fn is_guild_ignored(&self, id: Option<GuildId>) -> bool {
+        let Some(id) = id else {
+            return self.ignore_direct_messages;
+        };
+
+        match self.guild_mode {
+            GuildMode::Blacklist => self.guild_blacklist.contains(id.as_u64()),
+            GuildMode::Allowlist => !self.guild_allowlist.contains(id.as_u64()),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Secrets {
+    #[serde(default)]
+    main_token: Option<Secret>,
+    #[serde(default)]
+    sub_tokens: Option<Vec<Secret>>,
+}
+
+impl Config {
+    /// Fills in tokens from the secrets file and environment, which take precedence
+    /// over anything written inline in `config.json`.
+    fn load_secrets(&mut self) -> Result<(), ConfigReadError> {
+        if let Some(path) = &self.secrets_file {
+            let secrets = read_secrets_file(path)?;
+            if let Some(main_token) = secrets.main_token {
+                self.main_token = main_token;
+            }
+            if let Some(sub_tokens) = secrets.sub_tokens {
+                self.sub_tokens = sub_tokens;
+            }
+        }
+
+        if let Ok(main_token) = env::var(MAIN_TOKEN_VAR)
+            && !main_token.trim().is_empty()
+        {
+            self.main_token = Secret::new(main_token.trim().to_string());
+        }
+
+        if let Ok(sub_tokens) = env::var(SUB_TOKENS_VAR) {
+            self.sub_tokens = sub_tokens
+                .split(',')
+                .map(str::trim)
+                .filter(|t| !t.is_empty())
+                .map(|t| Secret::new(t.to_string()))
+                .collect();
+        }
+
+        if self.main_token.is_empty() {
+            return Err(ConfigReadError::MissingMainToken);
+        }
+
+        Ok(())
+    }
+}
+
+/// A problem with a single config field, found by `Config::validate`.
+pub struct ConfigIssue {
+    field: String,
+    message: String,
+}
+
+impl ConfigIssue {
+    fn new(field: impl Into<String>, message: impl Into<String>) -> Self {
+        ConfigIssue {
+            field: field.into(),
+            message: message.into(),
         }
     }
+}
 
-    pub fn is_guild_blacklisted(&self, id: Option<GuildId>) -> bool {
-        id.map_or_else(|| false, |i| self.guild_blacklist.contains(i.as_u64()))
+impl fmt::Display for ConfigIssue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.field.underline(), self.message)
+    }
+}
+
+impl Config {
+    /// Checks every field without touching the network.
+    pub fn validate(&self) -> Vec<ConfigIssue> {
+        let mut issues = Vec::new();
+
+        if let Some(problem) = token_problem(&self.main_token) {
+            issues.push(ConfigIssue::new("main_token", problem));
+        }
+
+        let mut seen_tokens = HashSet::new();
+        for (index, token) in self.sub_tokens.iter().enumerate() {
+            let field = format!("sub_tokens[{}]", index);
+            if let Some(problem) = token_problem(token) {
+                issues.push(ConfigIssue::new(field, problem));
+            } else if *token == self.main_token {
+                issues.push(ConfigIssue::new(
+                    field,
+                    "is the same as main_token. Use snipe_on_main_token instead",
+                ));
+            } else if !seen_tokens.insert(token) {
+                issues.push(ConfigIssue::new(field, "is listed more than once"));
+            }
+        }
+
+        if !self.snipe_on_main_token && self.sub_tokens.is_empty() {
+            issues.push(ConfigIssue::new(
+                "snipe_on_main_token",
+                "is false and there are no sub_tokens, so nothing would be sniping",
+            ));
+        }
+
+        if let Some(path) = &self.secrets_file
+            && path.trim().is_empty()
+        {
+            issues.push(ConfigIssue::new(
+                "secrets_file",
+                "is empty. Remove it instead",
+            ));
+        }
+
+        if !self.webhook.is_empty()
+            && let Some(problem) = webhook_problem(&self.webhook)
+        {
+            issues.push(ConfigIssue::new("webhook", problem));
+        }
+
+        for (index, webhook) in self.webhooks.iter().enumerate() {
+            if let Some(problem) = webhook_problem(&webhook.url) {
+                issues.push(ConfigIssue::new(
+                    format!("webhooks[{}].url", index),
+                    problem,
+                ));
+            }
+        }
+
+        check_guild_ids("guild_blacklist", &self.guild_blacklist, &mut issues);
+        check_guild_ids("guild_allowlist", &self.guild_allowlist, &mut issues);
+
+        if self.guild_mode == GuildMode::Allowlist && self.guild_allowlist.is_empty() {
+            issues.push(ConfigIssue::new(
+                "guild_allowlist",
+                "is empty in allowlist mode, so every guild would be ignored",
+            ));
+        }
+
+        if self.location_cache_size == 0 {
+            issues.push(ConfigIssue::new(
+                "location_cache_size",
+                "must be at least 1",
+            ));
+        }
+
+        if self.location_cache_ttl_secs == 0 {
+            issues.push(ConfigIssue::new(
+                "location_cache_ttl_secs",
+                "must be at least 1 second",
+            ));
+        }
+
+        match self.api_base.parse::<Uri>() {
+            Ok(uri)
+                if matches!(uri.scheme_str(), Some("http") | Some("https"))
+                    && uri.host().is_some() => {}
+            _ => issues.push(ConfigIssue::new(
+                "api_base",
+                "must be an http(s) URL like https://discord.com/api/v9",
+            )),
+        }
+
+        issues
+    }
+}
+
+fn check_guild_ids(name: &str, ids: &[u64], issues: &mut Vec<ConfigIssue>) {
+    let mut seen = HashSet::new();
+    for (index, id) in ids.iter().enumerate() {
+        let field = format!("{}[{}]", name, index);
+        if *id == 0 {
+            issues.push(ConfigIssue::new(field, "is not a valid guild ID"));
+        } else if !seen.insert(id) {
+            issues.push(ConfigIssue::new(
+                field,
+                format!("{} is listed more than once", id),
+            ));
+        }
+    }
+}
+
+fn token_problem(token: &Secret) -> Option<&'static str> {
+    if token.is_empty() {
+        Some("is empty")
+    } else if token.expose() == PLACEHOLDER_TOKEN {
+        Some("still contains the placeholder token")
+    } else if token.expose().chars().any(char::is_whitespace) {
+        Some("contains whitespace")
+    } else {
+        None
+    }
+}
+
+fn webhook_problem(webhook: &str) -> Option<&'static str> {
+    let Ok(uri) = webhook.parse::<Uri>() else {
+        return Some("is not a valid URL");
+    };
+    if uri.scheme_str() != Some("https") {
+        Some("must be an https:// URL")
+    } else if !uri.host().is_some_and(|host| WEBHOOK_HOSTS.contains(&host)) {
+        Some("must point at discord.com")
+    } else if !uri.path().starts_with("/api/webhooks/") {
+        Some("must be a webhook URL like https://discord.com/api/webhooks/<id>/<token>")
+    } else {
+        None
     }
 }
 
@@ -57,20 +375,27 @@ pub enum ConfigReadError {
     NoSuchFile,
     FailedReading,
     MalformedConfig(String),
+    NoSuchSecretsFile(String),
+    MalformedSecrets(String),
+    InsecureSecrets(String, u32),
+    MissingMainToken,
+    Invalid(Vec<ConfigIssue>),
 }
 
 impl ConfigReadError {
-    pub fn handle(&self) {
+    pub fn handle(&self) -> ! {
         match self {
             ConfigReadError::NoSuchFile => match create_config() {
                 Ok(_) => {
                     log_error_and_exit!(
+                        reason: ExitReason::Config,
                         "No previous config file found. Please change your configuration in the {} file just created!",
                         "config.json".underline()
                     );
                 }
                 Err(_) => {
                     log_error_and_exit!(
+                        reason: ExitReason::Config,
                         "No previous config file found. Please create a {} file with your configuration!",
                         "config.json".underline()
                     );
@@ -78,10 +403,55 @@ impl ConfigReadError {
             },
             ConfigReadError::MalformedConfig(reason) => {
                 pretty_error!("Config file couldn't be read. Did you format it correctly?");
-                log_error_and_exit!("...{}.", reason);
+                log_error_and_exit!(reason: ExitReason::Config, "...{}.", reason);
             }
             ConfigReadError::FailedReading => {
-                log_error_and_exit!("Config file couldn't be opened...");
+                log_error_and_exit!(
+                    reason: ExitReason::Config,
+                    "Config file couldn't be opened..."
+                );
+            }
+            ConfigReadError::NoSuchSecretsFile(path) => {
+                log_error_and_exit!(
+                    reason: ExitReason::Config,
+                    "Secrets file {} couldn't be opened...",
+                    path.underline()
+                );
+            }
+            ConfigReadError::MalformedSecrets(reason) => {
+                pretty_error!("Secrets file couldn't be read. Did you format it correctly?");
+                log_error_and_exit!(reason: ExitReason::Config, "...{}.", reason);
+            }
+            ConfigReadError::InsecureSecrets(path, mode) => {
+                pretty_error!(
+                    "Secrets file {} can be read by other users (mode {:o}).",
+                    path.underline(),
+                    mode
+                );
+                log_error_and_exit!(
+                    reason: ExitReason::Config,
+                    "Restrict it with `chmod 600 {}` and try again!",
+                    path
+                );
+            }
+            ConfigReadError::MissingMainToken => {
+                log_error_and_exit!(
+                    reason: ExitReason::Config,
+                    "No main token found. Set it in {}, your secrets file or the {} variable!",
+                    "config.json".underline(),
+                    MAIN_TOKEN_VAR
+                );
+            }
+            ConfigReadError::Invalid(issues) => {
+                pretty_error!("Config file has {} problem(s):", issues.len());
+                for issue in issues {
+                    pretty_error!("...{}", issue);
+                }
+                log_error_and_exit!(
+                    reason: ExitReason::Config,
+                    "Fix the fields above in {} and try again!",
+                    "config.json".underline()
+                );
             }
         }
     }
@@ -97,8 +467,67 @@ pub fn try_read_config() -> Result<Config, ConfigReadError> {
     let mut contents = String::new();
     file.read_to_string(&mut contents)
         .map_err(|_| ConfigReadError::FailedReading)?;
-    serde_json::from_str::<Config>(&contents)
-        .map_err(|e| ConfigReadError::MalformedConfig(e.to_string()))
+    let mut config = serde_json::from_str::<Config>(&contents)
+        .map_err(|e| ConfigReadError::MalformedConfig(e.to_string()))?;
+    config.load_secrets()?;
+
+    let issues = config.validate();
+    if !issues.is_empty() {
+        return Err(ConfigReadError::Invalid(issues));
+    }
+    Ok(config)
+}
+
+/// Validates the config for `longshot check-config`, without creating one if it's missing.
+pub fn check_config() {
+    match try_read_config() {
+        Ok(_) => {
+            pretty_success!("Config file is valid!");
+        }
+        Err(ConfigReadError::NoSuchFile) => {
+            log_error_and_exit!(
+                reason: ExitReason::Config,
+                "No {} file found to check.",
+                "config.json".underline()
+            );
+        }
+        Err(e) => e.handle(),
+    }
+}
+
+fn read_secrets_file(path: &str) -> Result<Secrets, ConfigReadError> {
+    let mut file =
+        File::open(path).map_err(|_| ConfigReadError::NoSuchSecretsFile(path.to_string()))?;
+    check_secrets_permissions(&file, path)?;
+    let mut contents = String::new();
+    file.read_to_string(&mut contents)
+        .map_err(|_| ConfigReadError::NoSuchSecretsFile(path.to_string()))?;
+    serde_json::from_str::<Secrets>(&contents)
+        .map_err(|e| ConfigReadError::MalformedSecrets(e.to_string()))
+}
+
+#[cfg(unix)]
+fn check_secrets_permissions(file: &File, path: &str) -> Result<(), ConfigReadError> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let mode = file
+        .metadata()
+        .map_err(|_| ConfigReadError::NoSuchSecretsFile(path.to_string()))?
+        .permissions()
+        .mode();
+    if mode & 0o044 != 0 {
+        Err(ConfigReadError::InsecureSecrets(
+            path.to_string(),
+            mode & 0o777,
+        ))
+    } else {
+        Ok(())
+    }
+}
+
+#[cfg(not(unix))]
+fn check_secrets_permissions(_file: &File, _path: &str) -> Result<(), ConfigReadError> {
+    Ok(())
 }
 
 fn create_config() -> Result<(), ConfigWriteError> {
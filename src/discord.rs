@@ -1,32 +1,40 @@
-use crate::cache::LocationCache;
+use crate::cache::{Location, LocationCache};
 use crate::config::Config;
-use crate::logging::LogBlock;
+use crate::history::{History, HistoryEntry};
+use crate::logging::{ExitReason, LogBlock};
 use crate::matcher::get_gift_code;
+use crate::session::SessionStats;
 use crate::util::user_to_tag;
-use crate::webhook::Webhook;
+use crate::webhook::WebhookQueue;
 use crate::{log_error_and_exit, pretty_error, pretty_info, pretty_success, pretty_warn};
 use colored::*;
 use dashmap::DashSet;
 use hyper::client::HttpConnector;
 use hyper::{Body, Client, Method, Request, StatusCode};
 use hyper_tls::HttpsConnector;
-use log::{error, info};
+use log::{error, info, warn};
 use once_cell::sync::OnceCell;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use serenity::async_trait;
 use serenity::http::{CacheHttp, GuildPagination, Http};
-use serenity::model::channel::Message;
+use serenity::model::channel::{Channel, GuildChannel, Message};
 use serenity::model::gateway::Ready;
+use serenity::model::guild::{Guild, GuildUnavailable, PartialGuild};
 use serenity::model::id::GuildId;
 use serenity::model::user::CurrentUser;
 use serenity::prelude::{Context, EventHandler};
 use std::fmt;
-use std::sync::Arc;
 use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
-type HttpsClient = Client<HttpsConnector<HttpConnector>>;
+pub type HttpsClient = Client<HttpsConnector<HttpConnector>>;
 
-#[derive(Clone)]
+const BASE_INIT_RETRY_SECS: u64 = 5;
+const MAX_INIT_RETRY_SECS: u64 = 300;
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
 pub enum SnipeResult {
     Success,
     FakeOrExpired,
@@ -35,34 +43,88 @@ pub enum SnipeResult {
     DiscordError,
     ConnectionError,
     Unknown,
+    Observed,
+}
+
+impl fmt::Display for SnipeResult {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            SnipeResult::Success => "success",
+            SnipeResult::FakeOrExpired => "fake_or_expired",
+            SnipeResult::AlreadyRedeemed => "already_redeemed",
+            SnipeResult::RateLimited => "rate_limited",
+            SnipeResult::DiscordError => "discord_error",
+            SnipeResult::ConnectionError => "connection_error",
+            SnipeResult::Unknown => "unknown",
+            SnipeResult::Observed => "observed",
+        };
+        write!(f, "{}", name)
+    }
 }
 
 pub struct HandlerInfo {
     client: HttpsClient,
     config: Config,
     seen_codes: DashSet<String>,
+    history: History,
+    session: SessionStats,
+    webhooks: WebhookQueue,
+    location_cache: LocationCache,
     token_amount: usize,
     connected: AtomicUsize,
     total_guilds: AtomicUsize,
 }
 
 impl HandlerInfo {
-    pub fn new(client: HttpsClient, config: Config, token_amount: usize) -> Self {
+    pub fn new(client: HttpsClient, config: Config, history: History, token_amount: usize) -> Self {
+        let webhooks = WebhookQueue::new(config.webhooks(), &client);
+        let location_cache =
+            LocationCache::new(config.location_cache_size(), config.location_cache_ttl());
         HandlerInfo {
             client,
             config,
             seen_codes: DashSet::new(),
+            history,
+            session: SessionStats::default(),
+            webhooks,
+            location_cache,
             token_amount,
             connected: AtomicUsize::new(0),
             total_guilds: AtomicUsize::new(0),
         }
     }
+
+    pub fn session(&self) -> &SessionStats {
+        &self.session
+    }
+}
+
+/// Backs off `initialize_from_raw` after failures, so a rejected account isn't retried on every message.
+#[derive(Default)]
+struct InitRetry {
+    failures: u32,
+    not_before: Option<Instant>,
+}
+
+impl InitRetry {
+    fn is_due(&self) -> bool {
+        self.not_before.is_none_or(|at| Instant::now() >= at)
+    }
+
+    /// Records a failure and returns how long to wait before the next attempt.
+    fn failed(&mut self) -> Duration {
+        let delay = Duration::from_secs(BASE_INIT_RETRY_SECS << self.failures.min(6))
+            .min(Duration::from_secs(MAX_INIT_RETRY_SECS));
+        self.failures += 1;
+        self.not_before = Some(Instant::now() + delay);
+        delay
+    }
 }
 
 pub struct Handler {
     initialized: AtomicBool,
+    init_retry: Mutex<InitRetry>,
     profile: OnceCell<Profile>,
-    location_cache: LocationCache,
     info: Arc<HandlerInfo>,
 }
 
@@ -70,8 +132,8 @@ impl Handler {
     pub fn new(info: Arc<HandlerInfo>) -> Self {
         Handler {
             initialized: AtomicBool::new(false),
+            init_retry: Mutex::new(InitRetry::default()),
             profile: OnceCell::new(),
-            location_cache: LocationCache::new(),
             info,
         }
     }
@@ -82,91 +144,34 @@ impl Handler {
         _message: &Message,
         log: &mut LogBlock<'_>,
     ) -> SnipeResult {
-        let request = Request::builder()
-            .method(Method::POST)
-            .uri(format!(
-                "https://discord.com/api/v9/entitlements/gift-codes/{}/redeem",
-                gift_code
-            ))
-            .header("Authorization", &self.info.config.main_token())
-            .header("Content-Length", 0)
-            .body(Body::empty())
-            .unwrap();
-
-        if let Ok(response) = self.info.client.request(request).await {
-            match response.status() {
-                StatusCode::OK => {
-                    pretty_success!(log: log, "Yay! Claimed code!");
-                    SnipeResult::Success
-                }
-                StatusCode::METHOD_NOT_ALLOWED => {
-                    pretty_error!(log: log, "There was an error on Discord's side.");
-                    SnipeResult::DiscordError
-                }
-                StatusCode::NOT_FOUND => {
-                    pretty_warn!(log: log, "Code was fake or expired.");
-                    SnipeResult::FakeOrExpired
-                }
-                StatusCode::BAD_REQUEST => {
-                    pretty_error!(log: log, "Code was already redeemed.");
-                    SnipeResult::AlreadyRedeemed
-                }
-                StatusCode::TOO_MANY_REQUESTS => {
-                    pretty_warn!(log: log, "Rate-limited...");
-                    SnipeResult::RateLimited
-                }
-                unknown => {
-                    pretty_error!(
-                        log: log,
-                        "Received unknown response... ({}{})",
-                        unknown.as_str(),
-                        unknown
-                            .canonical_reason()
-                            .map_or_else(|| "".to_string(), |r| format!(" {}", r))
-                    );
-                    if let Ok(Ok(body)) = hyper::body::to_bytes(response.into_body())
-                        .await
-                        .map(|b| String::from_utf8(b.to_vec()))
-                    {
-                        pretty_error!(log: log, "...with this body: {}", body);
-                    } else {
-                        pretty_error!(
-                            log: log,
-                            "...and couldn't parse the body of the response."
-                        );
-                    }
-                    SnipeResult::Unknown
-                }
-            }
-        } else {
-            pretty_warn!(
-                log: log,
-                "Connection failed. Check network connection!"
-            );
-            SnipeResult::ConnectionError
-        }
+        redeem_code(
+            &gift_code,
+            self.info.config.main_token().expose(),
+            self.info.config.api_base(),
+            &self.info.client,
+            log,
+        )
+        .await
     }
 
-    async fn send_webhook(&self, message: &Message, result: SnipeResult) {
-        if let Some(webhook_url) = self.info.config.webhook() {
-            let webhook = Webhook::new(webhook_url);
-            let client = self.info.client.clone();
-            let profile = self.profile.get().unwrap().clone();
-            let msg = message.clone();
-
-            tokio::spawn(async move {
-                let _ = webhook.send(&msg, &client, &profile, result).await;
-            });
-        }
+    fn send_webhook(&self, message: &Message, profile: &Profile, result: &SnipeResult) {
+        self.info
+            .webhooks
+            .push(message, profile, result, &self.info.session);
     }
 
     fn initialize(&self, profile: Profile, guild_amount: usize) {
+        let Ok(profile) = self.profile.try_insert(profile) else {
+            pretty_warn!("Account was already initialized. Ignoring duplicate connection...");
+            return;
+        };
+
         pretty_info!(
             "Connected as {}! Now sniping in {} guilds...",
             profile.to_string().as_str().bright_blue().bold(),
             guild_amount.to_string().as_str().bright_blue().bold()
         );
-        self.profile.set(profile).unwrap();
+        self.info.session.connected(profile);
         self.info
             .total_guilds
             .fetch_add(guild_amount, Ordering::Relaxed);
@@ -182,28 +187,56 @@ impl Handler {
         }
     }
 
+    fn initialization_due(&self) -> bool {
+        !self.initialized.load(Ordering::Relaxed)
+            && self.init_retry.lock().is_ok_and(|retry| retry.is_due())
+    }
+
+    /// Initializes from the REST API when a message arrives before `ready`.
+    /// On failure, the next attempt waits for a later message after a growing delay.
     async fn initialize_from_raw(&self, http: &Http) {
-        self.initialized.store(true, Ordering::Relaxed);
-        let profile = Profile::from(http.get_current_user().await.unwrap());
-        let guild_amount = http
-            .get_guilds(&GuildPagination::After(GuildId(0)), 100)
-            .await
-            .unwrap()
-            .len();
-        self.initialize(profile, guild_amount);
+        if self.initialized.swap(true, Ordering::Relaxed) {
+            return;
+        }
+
+        let account = async {
+            let user = http.get_current_user().await?;
+            let guilds = http
+                .get_guilds(&GuildPagination::After(GuildId(0)), 100)
+                .await?;
+            Ok::<_, serenity::Error>((user, guilds.len()))
+        };
+
+        match account.await {
+            Ok((user, guild_amount)) => self.initialize(Profile::from(user), guild_amount),
+            Err(e) => {
+                let delay = self
+                    .init_retry
+                    .lock()
+                    .map(|mut retry| retry.failed())
+                    .unwrap_or(Duration::from_secs(MAX_INIT_RETRY_SECS));
+                pretty_error!(
+                    "Failed initializing an account, retrying in {}s... ({})",
+                    delay.as_secs(),
+                    e
+                );
+                self.initialized.store(false, Ordering::Relaxed);
+            }
+        }
     }
 }
 
 #[async_trait]
 impl EventHandler for Handler {
     async fn message(&self, ctx: Context, msg: Message) {
-        if !self.initialized.load(Ordering::Relaxed) {
+        if self.initialization_due() {
             self.initialize_from_raw(ctx.http()).await;
-        } else if self.profile.get().is_none() {
-            return;
         }
+        let Some(profile) = self.profile.get() else {
+            return;
+        };
 
-        if self.info.config.is_guild_blacklisted(msg.guild_id) {
+        if self.info.config.is_guild_ignored(msg.guild_id) {
             return;
         }
 
@@ -212,19 +245,67 @@ impl EventHandler for Handler {
         };
 
         if self.info.seen_codes.insert(gift_code.clone()) {
-            let mut log = LogBlock::new(self.profile.get().unwrap());
-            pretty_info!(log: log, "Claiming code: {}!", gift_code);
+            let _pending = self.info.session.begin_pending();
+            let mut log = LogBlock::new(profile);
 
-            let result = self.make_request(gift_code, &msg, &mut log).await;
+            let result = if self.info.config.is_dry_run() {
+                pretty_info!(log: log, "Observed code: {}! (dry-run)", gift_code);
+                SnipeResult::Observed
+            } else {
+                pretty_info!(log: log, "Claiming code: {}!", gift_code);
+                self.make_request(gift_code.clone(), &msg, &mut log).await
+            };
             log.freeze_time();
 
             let location = self
+                .info
                 .location_cache
                 .get_and_cache_location(msg.channel_id, msg.guild_id, ctx.http())
                 .await;
-            log.send(location, user_to_tag(&msg.author));
+            let entry = HistoryEntry::new(
+                profile,
+                &msg,
+                location.as_ref().unwrap_or(&Location::default()),
+                user_to_tag(&msg.author),
+                gift_code,
+                result.clone(),
+            );
+            log.send(location, user_to_tag(&msg.author), &result);
+            self.info.history.record(&entry);
+            self.info.session.record(profile, &result);
 
-            self.send_webhook(&msg, result).await;
+            self.send_webhook(&msg, profile, &result);
+        }
+    }
+
+    async fn channel_update(&self, _ctx: Context, _old: Option<Channel>, new: Channel) {
+        self.info.location_cache.update_channel(new);
+    }
+
+    async fn channel_delete(&self, _ctx: Context, channel: &GuildChannel) {
+        self.info.location_cache.remove_channel(channel.id);
+    }
+
+    async fn guild_update(
+        &self,
+        _ctx: Context,
+        _old_data_if_available: Option<Guild>,
+        new_but_incomplete: PartialGuild,
+    ) {
+        self.info
+            .location_cache
+            .update_guild(new_but_incomplete.id, new_but_incomplete.name);
+    }
+
+    async fn guild_delete(
+        &self,
+        _ctx: Context,
+        incomplete: GuildUnavailable,
+        _full: Option<Guild>,
+    ) {
+        // Guilds going through an outage are only unavailable, not gone.
+        if !incomplete.unavailable {
+            self.info.location_cache.remove_guild(incomplete.id);
         }
     }
 
@@ -273,39 +354,133 @@ impl fmt::Display for Profile {
     }
 }
 
+#[derive(Debug, PartialEq)]
 pub enum ProfileError {
     Unauthorized,
     RateLimited,
     ConnectionError,
+    MalformedResponse(String),
     Other,
 }
 
 impl ProfileError {
-    pub fn handle(&self) {
+    pub fn handle(&self) -> ! {
         match self {
             ProfileError::Unauthorized => {
-                log_error_and_exit!("Main token verification failed. Check token validity.");
+                log_error_and_exit!(
+                    reason: ExitReason::Unauthorized,
+                    "Main token verification failed. Check token validity."
+                );
             }
             ProfileError::RateLimited => {
-                log_error_and_exit!("Rate-limited. Try again later...");
+                log_error_and_exit!(
+                    reason: ExitReason::RateLimited,
+                    "Rate-limited. Try again later..."
+                );
             }
             ProfileError::ConnectionError => {
-                log_error_and_exit!("Connection failed. Check network connection!");
+                log_error_and_exit!(
+                    reason: ExitReason::Connection,
+                    "Connection failed. Check network connection!"
+                );
+            }
+            ProfileError::MalformedResponse(reason) => {
+                log_error_and_exit!(
+                    reason: ExitReason::UnknownResponse,
+                    "Received a malformed profile from Discord... ({})",
+                    reason
+                );
             }
             ProfileError::Other => {
-                log_error_and_exit!("Received unknown response for Discord...");
+                log_error_and_exit!(
+                    reason: ExitReason::UnknownResponse,
+                    "Received unknown response for Discord..."
+                );
             }
         }
     }
 }
 
+pub async fn redeem_code(
+    gift_code: &str,
+    token: &str,
+    api_base: &str,
+    client: &HttpsClient,
+    log: &mut LogBlock<'_>,
+) -> SnipeResult {
+    let request = Request::builder()
+        .method(Method::POST)
+        .uri(format!(
+            "{}/entitlements/gift-codes/{}/redeem",
+            api_base, gift_code
+        ))
+        .header("Authorization", token)
+        .header("Content-Length", 0)
+        .body(Body::empty())
+        .unwrap();
+
+    if let Ok(response) = client.request(request).await {
+        match response.status() {
+            StatusCode::OK => {
+                pretty_success!(log: log, "Yay! Claimed code!");
+                SnipeResult::Success
+            }
+            StatusCode::METHOD_NOT_ALLOWED => {
+                pretty_error!(log: log, "There was an error on Discord's side.");
+                SnipeResult::DiscordError
+            }
+            StatusCode::NOT_FOUND => {
+                pretty_warn!(log: log, "Code was fake or expired.");
+                SnipeResult::FakeOrExpired
+            }
+            StatusCode::BAD_REQUEST => {
+                pretty_error!(log: log, "Code was already redeemed.");
+                SnipeResult::AlreadyRedeemed
+            }
+            StatusCode::TOO_MANY_REQUESTS => {
+                pretty_warn!(log: log, "Rate-limited...");
+                SnipeResult::RateLimited
+            }
+            unknown => {
+                pretty_error!(
+                    log: log,
+                    "Received unknown response... ({}{})",
+                    unknown.as_str(),
+                    unknown
+                        .canonical_reason()
+                        .map_or_else(|| "".to_string(), |r| format!(" {}", r))
+                );
+                if let Ok(Ok(body)) = hyper::body::to_bytes(response.into_body())
+                    .await
+                    .map(|b| String::from_utf8(b.to_vec()))
+                {
+                    pretty_error!(log: log, "...with this body: {}", body);
+                } else {
+                    pretty_error!(
+                        log: log,
+                        "...and couldn't parse the body of the response."
+                    );
+                }
+                SnipeResult::Unknown
+            }
+        }
+    } else {
+        pretty_warn!(
+            log: log,
+            "Connection failed. Check network connection!"
+        );
+        SnipeResult::ConnectionError
+    }
+}
+
 pub async fn get_profile_for_token(
     token: &str,
+    api_base: &str,
     client: &HttpsClient,
 ) -> Result<Profile, ProfileError> {
     let request = Request::builder()
         .method(Method::GET)
-        .uri("https://discord.com/api/v9/users/@me")
+        .uri(format!("{}/users/@me", api_base))
         .header("Authorization", token)
         .body(Body::empty())
         .unwrap();
@@ -315,14 +490,11 @@ pub async fn get_profile_for_token(
     if let Ok(response) = response_result {
         match response.status() {
             StatusCode::OK => {
-                let streamed_bytes = hyper::body::to_bytes(response.into_body()).await;
-                if let Ok(bytes) = streamed_bytes {
-                    let body = String::from_utf8(bytes.to_vec()).expect("Received bad stream.");
-                    let profile = serde_json::from_str(&body).expect("Malformed response.");
-                    Ok(profile)
-                } else {
-                    Err(ProfileError::Other)
-                }
+                let bytes = hyper::body::to_bytes(response.into_body())
+                    .await
+                    .map_err(|_| ProfileError::ConnectionError)?;
+                serde_json::from_slice(&bytes)
+                    .map_err(|e| ProfileError::MalformedResponse(e.to_string()))
             }
             StatusCode::UNAUTHORIZED => Err(ProfileError::Unauthorized),
             StatusCode::TOO_MANY_REQUESTS => Err(ProfileError::RateLimited),
@@ -0,0 +1,179 @@
+use crate::cache::Location;
+use crate::discord::{Profile, SnipeResult};
+use crate::{pretty_info, pretty_warn};
+use chrono::{DateTime, Local, NaiveDate};
+use colored::*;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use serenity::model::channel::Message;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::sync::Mutex;
+
+pub const HISTORY_FILE: &str = "history.jsonl";
+
+/// One detected gift code, as stored in the history file.
+#[derive(Deserialize, Serialize)]
+pub struct HistoryEntry {
+    timestamp: String,
+    account: String,
+    guild_id: Option<u64>,
+    guild: Option<String>,
+    channel_id: u64,
+    channel: String,
+    sender: String,
+    code: String,
+    result: SnipeResult,
+}
+
+impl HistoryEntry {
+    pub fn new(
+        profile: &Profile,
+        message: &Message,
+        location: &Location,
+        sender: String,
+        code: String,
+        result: SnipeResult,
+    ) -> Self {
+        HistoryEntry {
+            timestamp: Local::now().to_rfc3339(),
+            account: profile.to_string(),
+            guild_id: message.guild_id.map(|g| g.0),
+            guild: location.guild_name.as_deref().cloned(),
+            channel_id: message.channel_id.0,
+            channel: location.channel_name.clone(),
+            sender,
+            code,
+            result,
+        }
+    }
+
+    fn date(&self) -> Option<NaiveDate> {
+        DateTime::parse_from_rfc3339(&self.timestamp)
+            .ok()
+            .map(|t| t.with_timezone(&Local).date_naive())
+    }
+}
+
+/// Append-only log of every detection, kept across sessions.
+pub struct History {
+    file: Option<Mutex<File>>,
+}
+
+impl History {
+    pub fn open(path: &str) -> Self {
+        let file = OpenOptions::new().create(true).append(true).open(path);
+        if file.is_err() {
+            pretty_warn!(
+                "Couldn't open {}. Events won't be saved to the history!",
+                path.underline()
+            );
+        }
+
+        History {
+            file: file.ok().map(Mutex::new),
+        }
+    }
+
+    pub fn record(&self, entry: &HistoryEntry) {
+        let Some(file) = &self.file else {
+            return;
+        };
+
+        let written = match (serde_json::to_string(entry), file.lock()) {
+            (Ok(json), Ok(mut file)) => writeln!(file, "{}", json).is_ok(),
+            _ => false,
+        };
+        if !written {
+            pretty_warn!("Failed saving event to the history.");
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct HistoryFilter {
+    pub account: Option<String>,
+    pub guild: Option<String>,
+    pub result: Option<SnipeResult>,
+    pub since: Option<NaiveDate>,
+    pub until: Option<NaiveDate>,
+}
+
+impl HistoryFilter {
+    fn matches(&self, entry: &HistoryEntry) -> bool {
+        if let Some(account) = &self.account
+            && !entry.account.eq_ignore_ascii_case(account)
+        {
+            return false;
+        }
+
+        if let Some(guild) = &self.guild {
+            let by_id = entry.guild_id.is_some_and(|id| id.to_string() == *guild);
+            let by_name = entry
+                .guild
+                .as_ref()
+                .is_some_and(|name| name.eq_ignore_ascii_case(guild));
+            if !by_id && !by_name {
+                return false;
+            }
+        }
+
+        if let Some(result) = &self.result
+            && entry.result != *result
+        {
+            return false;
+        }
+
+        if self.since.is_some() || self.until.is_some() {
+            let Some(date) = entry.date() else {
+                return false;
+            };
+            if self.since.is_some_and(|since| date < since)
+                || self.until.is_some_and(|until| date > until)
+            {
+                return false;
+            }
+        }
+
+        true
+    }
+}
+
+/// Prints every entry in the history file that passes `filter`.
+pub fn print_history(path: &str, filter: &HistoryFilter) {
+    let Ok(file) = File::open(path) else {
+        pretty_info!("No history found yet in {}.", path.underline());
+        return;
+    };
+
+    let mut found = 0;
+    for line in BufReader::new(file).lines() {
+        let Ok(line) = line else {
+            break;
+        };
+        let Ok(entry) = serde_json::from_str::<HistoryEntry>(&line) else {
+            continue;
+        };
+        if !filter.matches(&entry) {
+            continue;
+        }
+
+        found += 1;
+        let location = match &entry.guild {
+            Some(guild) => format!("{} > {}", guild, entry.channel),
+            None => entry.channel.clone(),
+        };
+        let result = entry.result.to_string();
+        println!(
+            "{} › ({}) [{} > {}] {}: {}",
+            entry.timestamp,
+            entry.account,
+            location,
+            entry.sender,
+            entry.code,
+            result.bright_blue()
+        );
+    }
+
+    pretty_info!("Found {} matching event(s).", found);
+}
@@ -0,0 +1,11 @@
+pub mod cache;
+pub mod cli;
+pub mod config;
+pub mod discord;
+pub mod history;
+pub mod logging;
+pub mod matcher;
+pub mod secret;
+pub mod session;
+pub mod util;
+pub mod webhook;
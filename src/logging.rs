@@ -1,15 +1,34 @@
-use crate::cache::Location;
-use crate::discord::Profile;
+use crate::cache::{Location, LocationError};
+use crate::discord::{Profile, SnipeResult};
+use crate::secret::redact_tokens;
 use colored::*;
-use log::{Level, SetLoggerError};
-use std::io::{Read, Write, stdin, stdout};
+use log::{Level, SetLoggerError, error, info};
+use once_cell::sync::OnceCell;
+use serde::Serialize;
+use std::error::Error;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read, Write, stdin, stdout};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::time::Instant;
 
+/// Target for finished `LogBlock`s, printed to the console as-is.
+const BLOCK_TARGET: &str = "longshot::block";
+/// Target for JSON event records, only written to the JSON-lines sink.
+const EVENT_TARGET: &str = "longshot::event";
+
+static HEADLESS: AtomicBool = AtomicBool::new(false);
+static VERBOSE: AtomicBool = AtomicBool::new(false);
+static JSON_SINK: OnceCell<Mutex<File>> = OnceCell::new();
+
 #[macro_export]
 macro_rules! log_error_and_exit {
-    ($($arg:tt)+) => (
+    (reason: $reason:expr, $($arg:tt)+) => (
         error!("{}", format!($($arg)+));
-        $crate::logging::pause_exit();
+        $crate::logging::pause_exit($reason);
+    );
+    ($($arg:tt)+) => (
+        $crate::log_error_and_exit!(reason: $crate::logging::ExitReason::General, $($arg)+);
     )
 }
 
@@ -87,19 +106,30 @@ impl<'a> LogBlock<'a> {
         self.elapsed = Some(self.start.elapsed().as_millis());
     }
 
-    pub fn send(&mut self, location_cache: Result<Location, ()>, sender: String) {
+    pub fn send(
+        &mut self,
+        location_cache: Result<Location, LocationError>,
+        sender: String,
+        result: &SnipeResult,
+    ) {
         if self.elapsed.is_none() {
             self.freeze_time();
         }
 
-        let location = if let Ok(location) = location_cache {
-            location
-        } else {
-            pretty_error!(log: self, "Failed requesting location for event.");
-            Location::default()
+        let location = match location_cache {
+            Ok(location) => location,
+            Err(e) => {
+                pretty_error!(
+                    log: self,
+                    "Failed requesting location for event: {}.",
+                    describe_error(&e)
+                );
+                Location::default()
+            }
         };
+        let elapsed = self.elapsed.unwrap();
 
-        println!(
+        let mut block = format!(
             "\n{} › ({}) [{} > {}]",
             chrono::Local::now().format("%H:%M:%S"),
             self.profile,
@@ -108,10 +138,27 @@ impl<'a> LogBlock<'a> {
         );
 
         for message in &self.messages {
-            message.send();
+            block.push('\n');
+            block.push_str(&message.render());
         }
 
-        println!("Finished in: {}ms", self.elapsed.unwrap());
+        block.push_str(&format!("\nFinished in: {}ms", elapsed));
+        info!(target: BLOCK_TARGET, "{}", block);
+
+        let event = Event {
+            timestamp: chrono::Local::now().to_rfc3339(),
+            account: self.profile.to_string(),
+            location: EventLocation {
+                guild: location.guild_name.as_deref().map(String::as_str),
+                channel: &location.channel_name,
+            },
+            sender: &sender,
+            result,
+            elapsed_ms: elapsed,
+        };
+        if let Ok(json) = serde_json::to_string(&event) {
+            info!(target: EVENT_TARGET, "{}", json);
+        }
     }
 }
 
@@ -122,17 +169,34 @@ pub struct LogMessage {
 }
 
 impl LogMessage {
-    pub fn send(&self) {
+    pub fn render(&self) -> String {
         let text = if self.is_success {
             self.text.as_str().bright_green()
         } else {
             self.text.as_str().normal()
         };
 
-        println!(" ({}) {}", map_level(self.level), text);
+        format!(" ({}) {}", map_level(self.level), text)
     }
 }
 
+/// One JSON-lines record per finished `LogBlock`.
+#[derive(Serialize)]
+struct Event<'a> {
+    timestamp: String,
+    account: String,
+    location: EventLocation<'a>,
+    sender: &'a str,
+    result: &'a SnipeResult,
+    elapsed_ms: u128,
+}
+
+#[derive(Serialize)]
+struct EventLocation<'a> {
+    guild: Option<&'a str>,
+    channel: &'a str,
+}
+
 fn map_level(level: Level) -> ColoredString {
     match level {
         Level::Info => "+".cyan(),
@@ -149,21 +213,45 @@ pub fn set_up_logger() -> Result<(), SetLoggerError> {
         let _ = colored::control::set_virtual_terminal(true);
     }
 
-    fern::Dispatch::new()
+    let console = fern::Dispatch::new()
+        .filter(|metadata| metadata.target() != EVENT_TARGET)
         .format(move |out, message, record| {
-            out.finish(format_args!(
-                "{}› ({}) {}",
-                chrono::Local::now().format("%H:%M:%S"),
-                map_level(record.level()),
-                message
-            ))
+            let text = redact_tokens(&message.to_string()).into_owned();
+            if record.target() == BLOCK_TARGET {
+                out.finish(format_args!("{}", text))
+            } else {
+                out.finish(format_args!(
+                    "{}› ({}) {}",
+                    chrono::Local::now().format("%H:%M:%S"),
+                    map_level(record.level()),
+                    text
+                ))
+            }
         })
+        .chain(std::io::stdout());
+
+    let json = fern::Dispatch::new()
+        .filter(|metadata| metadata.target() == EVENT_TARGET)
+        .chain(fern::Output::call(|record| {
+            if let Some(sink) = JSON_SINK.get()
+                && let Ok(mut file) = sink.lock()
+            {
+                let _ = writeln!(file, "{}", redact_tokens(&record.args().to_string()));
+            }
+        }));
+
+    fern::Dispatch::new()
         .level_for("serenity", log::LevelFilter::Off)
         .level_for("tracing::span", log::LevelFilter::Off)
         .level(log::LevelFilter::Info)
-        .chain(std::io::stdout())
+        .chain(console)
+        .chain(json)
         .apply()?;
 
+    std::panic::set_hook(Box::new(|info| {
+        error!("{}", info);
+    }));
+
     println!(
         "{}\n",
         r".____                               .__            __   
@@ -178,10 +266,60 @@ pub fn set_up_logger() -> Result<(), SetLoggerError> {
     Ok(())
 }
 
-pub fn pause_exit() {
-    let mut stdout = stdout();
-    stdout.write_all(b"Press the enter key to exit...").unwrap();
-    stdout.flush().unwrap();
-    stdin().read_exact(&mut [0]).unwrap();
-    std::process::exit(1);
+/// Appends one JSON record per event to `path` from now on.
+pub fn enable_json_log(path: &str) -> io::Result<()> {
+    let file = OpenOptions::new().create(true).append(true).open(path)?;
+    let _ = JSON_SINK.set(Mutex::new(file));
+    Ok(())
+}
+
+/// Process exit codes, one for each class of fatal failure.
+#[derive(Clone, Copy)]
+pub enum ExitReason {
+    General = 1,
+    Usage = 2,
+    Config = 3,
+    Unauthorized = 4,
+    RateLimited = 5,
+    Connection = 6,
+    UnknownResponse = 7,
+}
+
+pub fn set_headless(headless: bool) {
+    HEADLESS.store(headless, Ordering::Relaxed);
+}
+
+pub fn is_headless() -> bool {
+    HEADLESS.load(Ordering::Relaxed)
+}
+
+pub fn set_verbose(verbose: bool) {
+    VERBOSE.store(verbose, Ordering::Relaxed);
+}
+
+pub fn is_verbose() -> bool {
+    VERBOSE.load(Ordering::Relaxed)
+}
+
+/// Formats an error for the log, followed by its whole chain of causes in verbose mode.
+pub fn describe_error(error: &dyn Error) -> String {
+    let mut text = error.to_string();
+    if is_verbose() {
+        let mut source = error.source();
+        while let Some(cause) = source {
+            text.push_str(&format!(", caused by: {}", cause));
+            source = cause.source();
+        }
+    }
+    text
+}
+
+pub fn pause_exit(reason: ExitReason) -> ! {
+    if !is_headless() {
+        let mut stdout = stdout();
+        let _ = stdout.write_all(b"Press the enter key to exit...");
+        let _ = stdout.flush();
+        let _ = stdin().read_exact(&mut [0]);
+    }
+    std::process::exit(reason as i32);
 }
@@ -1,31 +1,57 @@
-mod cache;
-mod config;
-mod discord;
-mod logging;
-mod matcher;
-mod util;
-mod webhook;
-
 use colored::*;
 use hyper::{Body, Client};
 use hyper_tls::HttpsConnector;
-use log::{error, info};
+use log::{error, info, warn};
+use longshot::logging::ExitReason;
+use longshot::{
+    cli, config, discord, history, log_error_and_exit, logging, pretty_error, pretty_info,
+    pretty_warn,
+};
 use serenity::Client as DiscordClient;
 use std::sync::Arc;
+use std::time::Duration;
+
+const SHUTDOWN_TIMEOUT_SECS: u64 = 5;
 
 #[tokio::main]
 async fn main() {
     logging::set_up_logger().expect("Failed setting up logger.");
+    let args = cli::Args::parse();
+
+    if let Some(path) = &args.json_log
+        && let Err(e) = logging::enable_json_log(path)
+    {
+        log_error_and_exit!(
+            reason: ExitReason::Config,
+            "Couldn't open JSON log {}... ({})",
+            path.underline(),
+            e
+        );
+    }
+
+    match &args.command {
+        cli::Command::Run => {}
+        cli::Command::CheckConfig => {
+            config::check_config();
+            return;
+        }
+        cli::Command::History(filter) => {
+            history::print_history(history::HISTORY_FILE, filter);
+            return;
+        }
+    }
 
     let https = HttpsConnector::new();
     let client = Client::builder().build::<_, Body>(https);
 
-    let config = config::try_read_config().map_err(|e| e.handle()).unwrap();
-    let main_token = config.main_token();
-    let main_profile = discord::get_profile_for_token(&main_token, &client)
-        .await
-        .map_err(|e| e.handle())
-        .unwrap();
+    let mut config = config::try_read_config().unwrap_or_else(|e| e.handle());
+    if args.dry_run {
+        config.enable_dry_run();
+    }
+    let main_profile =
+        discord::get_profile_for_token(config.main_token().expose(), config.api_base(), &client)
+            .await
+            .unwrap_or_else(|e| e.handle());
 
     pretty_info!(
         "Starting Nitro sniping for {}!\n",
@@ -37,7 +63,10 @@ async fn main() {
     sniping_tokens.dedup();
 
     if sniping_tokens.is_empty() {
-        log_error_and_exit!("At least one token is required to start sniping...");
+        log_error_and_exit!(
+            reason: ExitReason::Config,
+            "At least one token is required to start sniping..."
+        );
     }
 
     pretty_info!(
@@ -45,20 +74,37 @@ async fn main() {
         sniping_tokens.len()
     );
 
+    if let Some(allowlist) = config.guild_allowlist() {
+        pretty_info!(
+            "Only acting in {} allowlisted guild(s).\n",
+            allowlist.len().to_string().as_str().bright_blue().bold()
+        );
+    }
+
+    if config.is_dry_run() {
+        pretty_info!(
+            "Running in {} mode. Codes will be detected but never redeemed.\n",
+            "dry-run".bright_yellow().bold()
+        );
+    }
+
     let handler_info = Arc::new(discord::HandlerInfo::new(
         client,
         config,
+        history::History::open(history::HISTORY_FILE),
         sniping_tokens.len(),
     ));
 
     let mut tasks = Vec::new();
+    let mut shard_managers = Vec::new();
 
     for (index, token) in sniping_tokens.iter().enumerate() {
-        let discord_client_result = DiscordClient::builder(token)
+        let discord_client_result = DiscordClient::builder(token.expose())
             .event_handler(discord::Handler::new(handler_info.clone()))
             .await;
 
         if let Ok(mut discord_client) = discord_client_result {
+            shard_managers.push(discord_client.shard_manager.clone());
             tasks.push(tokio::spawn(async move {
                 let connection_result = discord_client.start().await;
                 if connection_result.is_err() {
@@ -73,6 +119,46 @@ async fn main() {
         }
     }
 
-    futures::future::join_all(tasks).await;
-    log_error_and_exit!("Lost all connections.");
+    tokio::select! {
+        _ = futures::future::join_all(tasks) => {
+            log_error_and_exit!(reason: ExitReason::Connection, "Lost all connections.");
+        }
+        _ = wait_for_shutdown_signal() => {}
+    }
+
+    pretty_info!("Shutting down...");
+    for shard_manager in &shard_managers {
+        shard_manager.lock().await.shutdown_all().await;
+    }
+
+    let unfinished = handler_info
+        .session()
+        .drain(Duration::from_secs(SHUTDOWN_TIMEOUT_SECS))
+        .await;
+    if unfinished > 0 {
+        pretty_warn!(
+            "Gave up waiting on {} pending event(s) and webhook(s).",
+            unfinished
+        );
+    }
+
+    handler_info.session().print_summary();
+}
+
+/// Resolves on Ctrl-C, or on SIGTERM on Unix.
+async fn wait_for_shutdown_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{SignalKind, signal};
+
+        if let Ok(mut terminate) = signal(SignalKind::terminate()) {
+            tokio::select! {
+                _ = tokio::signal::ctrl_c() => {}
+                _ = terminate.recv() => {}
+            }
+            return;
+        }
+    }
+
+    let _ = tokio::signal::ctrl_c().await;
 }
@@ -22,14 +22,14 @@ pub fn get_gift_code(message: &Message) -> Option<String> {
     }
 }
 
-fn sanitize_markdown(dirty_string: &str) -> String {
+pub fn sanitize_markdown(dirty_string: &str) -> String {
     const MARKDOWN_CHARS: [char; 5] = ['*', '_', '`', '~', '|'];
     let mut output = dirty_string.to_string();
     output.retain(|c| !MARKDOWN_CHARS.contains(&c));
     output
 }
 
-fn get_code_legitimacy_probability(code: &str) -> f64 {
+pub fn get_code_legitimacy_probability(code: &str) -> f64 {
     let (mut lower, mut upper, mut numeric) = (0, 0, 0);
 
     for c in code.chars() {
@@ -0,0 +1,90 @@
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::fmt;
+
+const REDACTED: &str = "[REDACTED]";
+
+/// A Discord token that never shows up in `Debug` or `Display` output.
+#[derive(Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize, Serialize)]
+#[serde(transparent)]
+pub struct Secret(String);
+
+impl Secret {
+    pub fn new(value: String) -> Self {
+        Secret(value)
+    }
+
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.trim().is_empty()
+    }
+}
+
+impl fmt::Debug for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Secret({})", REDACTED)
+    }
+}
+
+impl fmt::Display for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", REDACTED)
+    }
+}
+
+/// Masks anything shaped like a Discord user token.
+pub fn redact_tokens(text: &str) -> Cow<'_, str> {
+    lazy_static! {
+        static ref TOKEN_PATTERN: Regex =
+            Regex::new(r"mfa\.[\w-]{20,}|[\w-]{24,}\.[\w-]{6}\.[\w-]{27,}").unwrap();
+    }
+    TOKEN_PATTERN.replace_all(text, REDACTED)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TOKEN_24: &str = "MTIzNDU2Nzg5MDEyMzQ1Njc4.GaBcDe.abcdefghijklmnopqrstuvwxyz0123456789AB";
+    const TOKEN_26: &str = "MTIzNDU2Nzg5MDEyMzQ1Njc4OQ.XyZ-12.ABCDEFGHIJKLMNOPQRSTUVWXYZa";
+    const MFA_TOKEN: &str = "mfa.abcdefghijklmnopqrstuvwxyz_ABCDEFGHIJ-0123456789";
+
+    #[test]
+    fn redacts_user_tokens() {
+        for token in [TOKEN_24, TOKEN_26, MFA_TOKEN] {
+            let text = format!("Failed logging in with {} (401)", token);
+            let redacted = redact_tokens(&text);
+
+            assert!(!redacted.contains(token), "{} was not redacted", token);
+            assert_eq!(
+                redacted,
+                format!("Failed logging in with {} (401)", REDACTED)
+            );
+        }
+    }
+
+    #[test]
+    fn keeps_gift_codes() {
+        for code in ["aBcDeFgHiJkLmNoP", "aBcDeFgHiJkLmNoPqRsTuVwX"] {
+            let text = format!("Sniped discord.gift/{} in #general", code);
+
+            assert_eq!(redact_tokens(&text), text);
+        }
+    }
+
+    #[test]
+    fn secret_formatting_hides_value() {
+        for token in [TOKEN_24, TOKEN_26, MFA_TOKEN] {
+            let secret = Secret::new(token.to_string());
+
+            assert!(!format!("{:?}", secret).contains(token));
+            assert!(!format!("{}", secret).contains(token));
+            assert_eq!(secret.expose(), token);
+        }
+    }
+}
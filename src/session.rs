@@ -0,0 +1,102 @@
+use crate::discord::{Profile, SnipeResult};
+use crate::pretty_info;
+use colored::*;
+use dashmap::DashMap;
+use log::info;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+struct AccountStats {
+    connected_at: Instant,
+    results: HashMap<SnipeResult, usize>,
+}
+
+/// Per-account statistics for the current run, printed on shutdown.
+#[derive(Default)]
+pub struct SessionStats {
+    accounts: DashMap<String, AccountStats>,
+    pending: Arc<AtomicUsize>,
+}
+
+impl SessionStats {
+    pub fn connected(&self, profile: &Profile) {
+        self.accounts.insert(
+            profile.to_string(),
+            AccountStats {
+                connected_at: Instant::now(),
+                results: HashMap::new(),
+            },
+        );
+    }
+
+    pub fn record(&self, profile: &Profile, result: &SnipeResult) {
+        if let Some(mut account) = self.accounts.get_mut(&profile.to_string()) {
+            *account.results.entry(result.clone()).or_insert(0) += 1;
+        }
+    }
+
+    /// Marks work that should finish before shutting down, until the guard is dropped.
+    pub fn begin_pending(&self) -> PendingGuard {
+        self.pending.fetch_add(1, Ordering::SeqCst);
+        PendingGuard(self.pending.clone())
+    }
+
+    /// Waits for pending work to finish, giving up after `timeout`.
+    /// Returns how many tasks were still running.
+    pub async fn drain(&self, timeout: Duration) -> usize {
+        let deadline = Instant::now() + timeout;
+        while self.pending.load(Ordering::SeqCst) > 0 && Instant::now() < deadline {
+            tokio::time::delay_for(Duration::from_millis(50)).await;
+        }
+        self.pending.load(Ordering::SeqCst)
+    }
+
+    pub fn print_summary(&self) {
+        pretty_info!("Session summary:");
+
+        let mut accounts: Vec<_> = self.accounts.iter().collect();
+        accounts.sort_by(|a, b| a.key().cmp(b.key()));
+
+        for account in accounts {
+            let mut results: Vec<_> = account
+                .results
+                .iter()
+                .map(|(result, count)| format!("{} {}", count, result))
+                .collect();
+            results.sort();
+            let results = if results.is_empty() {
+                "no codes seen".to_string()
+            } else {
+                results.join(", ")
+            };
+
+            pretty_info!(
+                "{} was connected for {}: {}",
+                account.key().as_str().bright_blue().bold(),
+                format_duration(account.connected_at.elapsed()),
+                results
+            );
+        }
+    }
+}
+
+/// Handle returned by `SessionStats::begin_pending`.
+pub struct PendingGuard(Arc<AtomicUsize>);
+
+impl Drop for PendingGuard {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+fn format_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    format!(
+        "{}h {:02}m {:02}s",
+        seconds / 3600,
+        (seconds % 3600) / 60,
+        seconds % 60
+    )
+}
@@ -1,14 +1,84 @@
 use crate::discord::{Profile, SnipeResult};
+use crate::logging::{describe_error, is_verbose};
+use crate::secret::redact_tokens;
+use crate::session::{PendingGuard, SessionStats};
 use crate::util::user_to_tag;
+use crate::{pretty_error, pretty_warn};
 use hyper::client::HttpConnector;
-use hyper::{Body, Client, Method, Request, StatusCode};
+use hyper::{Body, Client, Method, Request, Response, StatusCode};
 use hyper_tls::HttpsConnector;
-use serde::Serialize;
+use log::{error, warn};
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use serenity::model::channel::{Embed, Message};
+use std::error::Error;
+use std::fmt;
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::sync::mpsc;
 
 type HttpsClient = Client<HttpsConnector<HttpConnector>>;
 
+const QUEUE_CAPACITY: usize = 64;
+const MAX_ATTEMPTS: u32 = 5;
+const BASE_BACKOFF_MS: u64 = 500;
+
+/// A webhook URL from the config, optionally limited to some results.
+#[derive(Clone, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct WebhookTarget {
+    pub url: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub results: Vec<SnipeResult>,
+}
+
+impl WebhookTarget {
+    fn accepts(&self, result: &SnipeResult) -> bool {
+        self.results.is_empty() || self.results.contains(result)
+    }
+}
+
+#[derive(Debug)]
+pub enum WebhookError {
+    RateLimited(Duration),
+    ServerError(StatusCode),
+    Rejected(StatusCode, String),
+    ConnectionError(hyper::Error),
+}
+
+impl WebhookError {
+    fn is_retryable(&self) -> bool {
+        !matches!(self, WebhookError::Rejected(..))
+    }
+}
+
+impl fmt::Display for WebhookError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            WebhookError::RateLimited(wait) => {
+                write!(f, "rate-limited for {}ms (HTTP 429)", wait.as_millis())
+            }
+            WebhookError::ServerError(status) => write!(f, "server error (HTTP {})", status),
+            WebhookError::Rejected(status, body) if body.is_empty() || !is_verbose() => {
+                write!(f, "rejected (HTTP {})", status)
+            }
+            WebhookError::Rejected(status, body) => {
+                write!(f, "rejected (HTTP {}) with body: {}", status, body)
+            }
+            WebhookError::ConnectionError(_) => write!(f, "connection failed"),
+        }
+    }
+}
+
+impl Error for WebhookError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            WebhookError::ConnectionError(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
 pub struct Webhook {
     pub url: String,
 }
@@ -24,24 +94,149 @@ impl Webhook {
         client: &HttpsClient,
         finder: &Profile,
         result: SnipeResult,
-    ) -> Result<(), ()> {
+    ) -> Result<(), WebhookError> {
         let payload = WebhookPayload::new(message, finder, result);
         let request = Request::builder()
             .method(Method::POST)
             .uri(&self.url)
             .header("Content-Type", "application/json")
-            .body(Body::from(serde_json::to_string(&payload).unwrap()))
+            .body(Body::from(
+                redact_tokens(&serde_json::to_string(&payload).unwrap()).into_owned(),
+            ))
             .unwrap();
 
-        let response_result = client.request(request).await;
-        if let Ok(response) = response_result {
-            if let StatusCode::NO_CONTENT = response.status() {
-                Ok(())
-            } else {
-                Err(())
+        let response = client
+            .request(request)
+            .await
+            .map_err(WebhookError::ConnectionError)?;
+        match response.status() {
+            status if status.is_success() => Ok(()),
+            StatusCode::TOO_MANY_REQUESTS => {
+                Err(WebhookError::RateLimited(retry_after(response).await))
+            }
+            status if status.is_server_error() => Err(WebhookError::ServerError(status)),
+            status => {
+                let body = hyper::body::to_bytes(response.into_body())
+                    .await
+                    .map(|b| String::from_utf8_lossy(&b).into_owned())
+                    .unwrap_or_default();
+                Err(WebhookError::Rejected(status, body))
+            }
+        }
+    }
+}
+
+/// Reads how long to wait from a 429 response, in the body's `retry_after` or the header.
+async fn retry_after(response: Response<Body>) -> Duration {
+    #[derive(Deserialize)]
+    struct RateLimit {
+        retry_after: f64,
+    }
+
+    let header = response
+        .headers()
+        .get("Retry-After")
+        .and_then(|h| h.to_str().ok())
+        .and_then(|h| h.parse::<f64>().ok());
+    let body = hyper::body::to_bytes(response.into_body())
+        .await
+        .ok()
+        .and_then(|b| serde_json::from_slice::<RateLimit>(&b).ok())
+        .map(|r| r.retry_after);
+
+    body.or(header)
+        .filter(|seconds| seconds.is_finite() && *seconds >= 0.0)
+        .map_or_else(|| Duration::from_secs(1), Duration::from_secs_f64)
+}
+
+struct WebhookJob {
+    message: Message,
+    finder: Profile,
+    result: SnipeResult,
+    _pending: PendingGuard,
+}
+
+/// Delivers webhooks in the background, one bounded queue and worker per target.
+pub struct WebhookQueue {
+    targets: Vec<(WebhookTarget, Mutex<mpsc::Sender<WebhookJob>>)>,
+}
+
+impl WebhookQueue {
+    pub fn new(targets: Vec<WebhookTarget>, client: &HttpsClient) -> Self {
+        let targets = targets
+            .into_iter()
+            .enumerate()
+            .map(|(index, target)| {
+                let (sender, receiver) = mpsc::channel(QUEUE_CAPACITY);
+                let webhook = Webhook::new(target.url.clone());
+                tokio::spawn(deliver(index, webhook, client.clone(), receiver));
+                (target, Mutex::new(sender))
+            })
+            .collect();
+
+        WebhookQueue { targets }
+    }
+
+    pub fn push(
+        &self,
+        message: &Message,
+        finder: &Profile,
+        result: &SnipeResult,
+        session: &SessionStats,
+    ) {
+        for (index, (target, sender)) in self.targets.iter().enumerate() {
+            if !target.accepts(result) {
+                continue;
+            }
+
+            let job = WebhookJob {
+                message: message.clone(),
+                finder: finder.clone(),
+                result: result.clone(),
+                _pending: session.begin_pending(),
+            };
+            let queued = sender
+                .lock()
+                .map(|mut sender| sender.try_send(job).is_ok())
+                .unwrap_or(false);
+            if !queued {
+                pretty_warn!("Webhook #{} queue is full. Dropped a notification.", index);
             }
-        } else {
-            Err(())
+        }
+    }
+}
+
+async fn deliver(
+    index: usize,
+    webhook: Webhook,
+    client: HttpsClient,
+    mut receiver: mpsc::Receiver<WebhookJob>,
+) {
+    while let Some(job) = receiver.recv().await {
+        for attempt in 1..=MAX_ATTEMPTS {
+            let error = match webhook
+                .send(&job.message, &client, &job.finder, job.result.clone())
+                .await
+            {
+                Ok(()) => break,
+                Err(error) => error,
+            };
+
+            if !error.is_retryable() || attempt == MAX_ATTEMPTS {
+                pretty_error!(
+                    "Failed delivering webhook #{} after {} attempt(s): {}.",
+                    index,
+                    attempt,
+                    describe_error(&error)
+                );
+                break;
+            }
+
+            let wait = match error {
+                WebhookError::RateLimited(wait) => wait,
+                _ => Duration::from_millis(BASE_BACKOFF_MS << (attempt - 1)),
+            };
+            tokio::time::delay_for(wait).await;
         }
     }
 }
@@ -87,6 +282,11 @@ impl WebhookPayload {
                 "Received an unknown response from Discord.",
                 0x000000,
             ),
+            SnipeResult::Observed => (
+                "Observed a gift code",
+                "Dry-run mode is enabled, so the code was not redeemed.",
+                0x7289DA,
+            ),
         };
 
         let embed = Embed::fake(|create| {
@@ -0,0 +1,36 @@
+use serde_json::json;
+use serenity::model::channel::Message;
+
+/// Builds a guild message with `content`, the way it would arrive over the gateway.
+pub fn message_with_content(content: &str) -> Message {
+    serde_json::from_value(json!({
+        "id": "1000000000000000001",
+        "type": 0,
+        "channel_id": "1000000000000000002",
+        "guild_id": "1000000000000000003",
+        "author": {
+            "id": "1000000000000000004",
+            "username": "gifter",
+            "discriminator": "0001",
+            "avatar": null,
+            "bot": false
+        },
+        "content": content,
+        "timestamp": "2021-01-01T00:00:00.000000+00:00",
+        "edited_timestamp": null,
+        "tts": false,
+        "mention_everyone": false,
+        "mentions": [],
+        "mention_roles": [],
+        "mention_channels": [],
+        "attachments": [],
+        "embeds": [],
+        "reactions": [],
+        "pinned": false,
+        "flags": 0,
+        "stickers": [],
+        "sticker_items": [],
+        "components": []
+    }))
+    .expect("Fixture message should deserialize.")
+}
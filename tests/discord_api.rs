@@ -0,0 +1,193 @@
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Client, Method, Request, Response, Server, StatusCode};
+use hyper_tls::HttpsConnector;
+use longshot::discord::{self, HttpsClient, Profile, ProfileError, SnipeResult};
+use longshot::logging::LogBlock;
+use std::convert::Infallible;
+use std::net::TcpListener;
+
+const TOKEN: &str = "test-token";
+const GIFT_CODE: &str = "aB3dE5gH7jK9mN1p";
+
+/// Starts a stand-in Discord API on a random local port and returns its base URL.
+///
+/// Requests matching `method` and `path` with the expected token get `status` and `body`,
+/// everything else gets a `418` so a malformed request never passes as the right result.
+fn spawn_api(method: Method, path: &'static str, status: StatusCode, body: &'static str) -> String {
+    let make_service = make_service_fn(move |_| {
+        let method = method.clone();
+        async move {
+            Ok::<_, Infallible>(service_fn(move |request: Request<Body>| {
+                let matches = request.method() == method
+                    && request.uri().path() == path
+                    && request
+                        .headers()
+                        .get("Authorization")
+                        .is_some_and(|token| token == TOKEN);
+                async move {
+                    let status = if matches {
+                        status
+                    } else {
+                        StatusCode::IM_A_TEAPOT
+                    };
+                    Ok::<_, Infallible>(
+                        Response::builder()
+                            .status(status)
+                            .body(Body::from(body))
+                            .unwrap(),
+                    )
+                }
+            }))
+        }
+    });
+
+    let server = Server::bind(&([127, 0, 0, 1], 0).into()).serve(make_service);
+    let api_base = format!("http://{}/api/v9", server.local_addr());
+    tokio::spawn(server);
+    api_base
+}
+
+/// Returns a base URL pointing at a local port nothing is listening on.
+fn closed_api() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+    drop(listener);
+    format!("http://{}/api/v9", address)
+}
+
+fn client() -> HttpsClient {
+    Client::builder().build::<_, Body>(HttpsConnector::new())
+}
+
+fn profile() -> Profile {
+    serde_json::from_str(r#"{"username": "tester", "avatar": null, "id": "1"}"#).unwrap()
+}
+
+async fn redeem_with_status(status: StatusCode) -> SnipeResult {
+    let api_base = spawn_api(
+        Method::POST,
+        "/api/v9/entitlements/gift-codes/aB3dE5gH7jK9mN1p/redeem",
+        status,
+        "{}",
+    );
+    let profile = profile();
+    let mut log = LogBlock::new(&profile);
+    discord::redeem_code(GIFT_CODE, TOKEN, &api_base, &client(), &mut log).await
+}
+
+async fn profile_with_status(
+    status: StatusCode,
+    body: &'static str,
+) -> Result<Profile, ProfileError> {
+    let api_base = spawn_api(Method::GET, "/api/v9/users/@me", status, body);
+    discord::get_profile_for_token(TOKEN, &api_base, &client()).await
+}
+
+#[tokio::test]
+async fn redeem_ok_is_success() {
+    assert_eq!(
+        redeem_with_status(StatusCode::OK).await,
+        SnipeResult::Success
+    );
+}
+
+#[tokio::test]
+async fn redeem_bad_request_is_already_redeemed() {
+    assert_eq!(
+        redeem_with_status(StatusCode::BAD_REQUEST).await,
+        SnipeResult::AlreadyRedeemed
+    );
+}
+
+#[tokio::test]
+async fn redeem_not_found_is_fake_or_expired() {
+    assert_eq!(
+        redeem_with_status(StatusCode::NOT_FOUND).await,
+        SnipeResult::FakeOrExpired
+    );
+}
+
+#[tokio::test]
+async fn redeem_method_not_allowed_is_discord_error() {
+    assert_eq!(
+        redeem_with_status(StatusCode::METHOD_NOT_ALLOWED).await,
+        SnipeResult::DiscordError
+    );
+}
+
+#[tokio::test]
+async fn redeem_too_many_requests_is_rate_limited() {
+    assert_eq!(
+        redeem_with_status(StatusCode::TOO_MANY_REQUESTS).await,
+        SnipeResult::RateLimited
+    );
+}
+
+#[tokio::test]
+async fn redeem_unknown_status_is_unknown() {
+    assert_eq!(
+        redeem_with_status(StatusCode::INTERNAL_SERVER_ERROR).await,
+        SnipeResult::Unknown
+    );
+}
+
+#[tokio::test]
+async fn redeem_without_server_is_connection_error() {
+    let profile = profile();
+    let mut log = LogBlock::new(&profile);
+    let result = discord::redeem_code(GIFT_CODE, TOKEN, &closed_api(), &client(), &mut log).await;
+    assert_eq!(result, SnipeResult::ConnectionError);
+}
+
+#[tokio::test]
+async fn profile_ok_is_parsed() {
+    let profile = profile_with_status(
+        StatusCode::OK,
+        r#"{"username": "sniper", "avatar": null, "id": "42"}"#,
+    )
+    .await
+    .unwrap();
+    assert_eq!(profile.to_string(), "sniper");
+}
+
+#[tokio::test]
+async fn profile_malformed_body_is_malformed_response() {
+    let result = profile_with_status(StatusCode::OK, "not json").await;
+    assert!(matches!(result, Err(ProfileError::MalformedResponse(_))));
+}
+
+#[tokio::test]
+async fn profile_unauthorized_is_unauthorized() {
+    assert_eq!(
+        profile_with_status(StatusCode::UNAUTHORIZED, "{}")
+            .await
+            .unwrap_err(),
+        ProfileError::Unauthorized
+    );
+}
+
+#[tokio::test]
+async fn profile_too_many_requests_is_rate_limited() {
+    assert_eq!(
+        profile_with_status(StatusCode::TOO_MANY_REQUESTS, "{}")
+            .await
+            .unwrap_err(),
+        ProfileError::RateLimited
+    );
+}
+
+#[tokio::test]
+async fn profile_unknown_status_is_other() {
+    assert_eq!(
+        profile_with_status(StatusCode::INTERNAL_SERVER_ERROR, "{}")
+            .await
+            .unwrap_err(),
+        ProfileError::Other
+    );
+}
+
+#[tokio::test]
+async fn profile_without_server_is_connection_error() {
+    let result = discord::get_profile_for_token(TOKEN, &closed_api(), &client()).await;
+    assert_eq!(result.unwrap_err(), ProfileError::ConnectionError);
+}
@@ -0,0 +1,65 @@
+mod common;
+
+use longshot::matcher::{get_code_legitimacy_probability, get_gift_code, sanitize_markdown};
+use serde_json::Value;
+
+const FIXTURES: &str = include_str!("fixtures/messages.json");
+
+#[test]
+fn fixtures_match_expected_codes() {
+    let fixtures: Vec<Value> = serde_json::from_str(FIXTURES).unwrap();
+    assert!(!fixtures.is_empty());
+
+    for fixture in fixtures {
+        let name = fixture["name"].as_str().unwrap();
+        let content = fixture["content"].as_str().unwrap();
+        let expected = fixture["expected"].as_str();
+
+        let message = common::message_with_content(content);
+        assert_eq!(
+            get_gift_code(&message).as_deref(),
+            expected,
+            "fixture: {}",
+            name
+        );
+    }
+}
+
+#[test]
+fn sanitize_markdown_strips_formatting() {
+    assert_eq!(sanitize_markdown("**bold**"), "bold");
+    assert_eq!(sanitize_markdown("__underline__"), "underline");
+    assert_eq!(sanitize_markdown("`code`"), "code");
+    assert_eq!(sanitize_markdown("~~strike~~"), "strike");
+    assert_eq!(sanitize_markdown("||spoiler||"), "spoiler");
+    assert_eq!(sanitize_markdown("*_`~|"), "");
+}
+
+#[test]
+fn sanitize_markdown_keeps_other_characters() {
+    let text = "https://discord.gift/aB3dE5gH7jK9mN1p # > - [link](url)";
+    assert_eq!(sanitize_markdown(text), text);
+}
+
+#[test]
+fn legitimacy_is_a_probability() {
+    for code in [
+        "aB3dE5gH7jK9mN1p",
+        "abcdefghijklmnop",
+        "ABCDEFGHIJKLMNOP",
+        "1234567890123456",
+    ] {
+        let probability = get_code_legitimacy_probability(code);
+        assert!((0.0..=1.0).contains(&probability), "code: {}", code);
+    }
+}
+
+#[test]
+fn legitimacy_favors_random_looking_codes() {
+    assert!(get_code_legitimacy_probability("aB3dE5gH7jK9mN1p") > 0.99);
+    assert!(get_code_legitimacy_probability("Xy7Kp2LmQ9rT4vWz") > 0.99);
+    assert!(get_code_legitimacy_probability("abcdefghijklMN12") < 0.5);
+    assert!(get_code_legitimacy_probability("abcdefghijklmnop") < 0.001);
+    assert!(get_code_legitimacy_probability("ABCDEFGHIJKLMNOP") < 0.001);
+    assert!(get_code_legitimacy_probability("1234567890123456") < 0.001);
+}